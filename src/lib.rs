@@ -1,3 +1,5 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 //! This tiny library provides unwrapping macros in situation where
 //! the typical unwrapping methods for Result and Option in the standard
 //! library comes short and the alternative is too verbose. Specifically when you want to have the `unwrap_or_else` logic
@@ -7,6 +9,15 @@
 //!
 //! This will log the error with eprintln! and skips the iteration.
 //! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! # use std::fmt;
+//! # #[derive(Debug)]
+//! # enum MyError { First, Second }
+//! # impl fmt::Display for MyError {
+//! #     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//! #         write!(f, "{:?}", self)
+//! #     }
+//! # }
 //! // with Result
 //! let some_stuff = vec![
 //!     Ok(1),
@@ -21,18 +32,34 @@
 //! }
 //! ```
 //! You can also supply a _closure-like_
-//! argument:
+//! argument. This takes over the fallback entirely, so nothing is logged
+//! automatically — do it yourself in the body if you want it:
 //! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! let some_stuff: Vec<Result<i32, &str>> = vec![Ok(1), Err("boom")];
 //! for thing in some_stuff {
 //!     let val = unwrap_or_else!(Result, thing, |e| {
 //!         // some code
 //!         eprintln!("Custom message for error: {e}");
-//!         continue,
+//!         continue
 //!     });
 //!     println!("{}", val);
 //! }
 //! ```
+//! Tag the call with `log` to get the automatic report anyway, on top of
+//! whatever the closure body does:
 //! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! let some_stuff: Vec<Result<i32, &str>> = vec![Ok(1), Err("boom")];
+//! for thing in some_stuff {
+//!     let val = unwrap_or_else!(Result, thing, log, |e| {
+//!         continue
+//!     });
+//!     println!("{}", val);
+//! }
+//! ```
+//! ```
+//! # use unwrap_macros::unwrap_or_else;
 //! // with Option
 //! let some_stuff = vec![
 //!     Some(1),
@@ -48,19 +75,175 @@
 //! ```
 //! This will log "No value" when `None` is matched.
 //! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! let some_stuff: Vec<Option<i32>> = vec![Some(1), None];
 //! for thing in some_stuff {
 //!     let val = unwrap_or_else!(Option, thing, "No value", continue);
 //!     println!("{}", val);
 //! }
 //! ```
+//! `Option` also accepts a closure-like argument, mirroring the `Result` one
+//! above; the bound name is just a placeholder, since `None` carries no value:
+//! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! let some_stuff: Vec<Option<i32>> = vec![Some(1), None];
+//! for thing in some_stuff {
+//!     let val = unwrap_or_else!(Option, thing, |_e| {
+//!         eprintln!("no value for this iteration");
+//!         continue
+//!     });
+//!     println!("{}", val);
+//! }
+//! ```
+//! and can additionally move extra state into the fallback body:
+//! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! fn first_or(opt: Option<i32>, default: i32) -> i32 {
+//!     unwrap_or_else!(Option, opt, |fallback| fallback, default)
+//! }
+//! ```
+//! Passing an empty closure `|| $v` instead opts out of the reporting step
+//! entirely, which keeps the expansion a plain `match` usable in `const fn`s
+//! and `const`/`static` initializers:
+//! ```
+//! # use unwrap_macros::unwrap_or_else;
+//! const fn first_or_zero(opt: Option<i32>) -> i32 {
+//!     unwrap_or_else!(Option, opt, || 0)
+//! }
+//! const FIRST: i32 = first_or_zero(Some(4));
+//! ```
+//!
+//! # Logging backend
+//!
+//! By default the error-reporting arms print to stderr with `eprintln!`. Enable
+//! the `log` feature to route the same message through the [`log`] crate's
+//! `log::error!` instead, so it flows into whatever logger the consumer of
+//! your binary/library has installed.
+//!
+//! # `no_std`
+//!
+//! The `std` feature is on by default. Disabling default features drops
+//! `eprintln!`, so the crate (and its macros) work in `no_std` contexts such
+//! as firmware or kernel-adjacent code; without the `log` feature as well,
+//! the reporting step is simply skipped rather than logged anywhere. Enable
+//! `log` alongside a `no_std` build to keep getting error messages, since
+//! `log::error!` itself doesn't require `std`.
+
+#[cfg(feature = "log")]
+#[doc(hidden)]
+pub use log as __log;
+
+/// Internal reporting shim so every error-reporting arm only has to pick
+/// between `log::error!`, `eprintln!` and a silent no-op in one place.
+#[cfg(feature = "log")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __unwrap_macros_report {
+    ($e:expr) => {
+        $crate::__log::error!("{}", $e)
+    };
+}
+
+/// Internal reporting shim so every error-reporting arm only has to pick
+/// between `log::error!`, `eprintln!` and a silent no-op in one place.
+#[cfg(all(not(feature = "log"), feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __unwrap_macros_report {
+    ($e:expr) => {
+        eprintln!("{}", $e)
+    };
+}
+
+/// Internal reporting shim so every error-reporting arm only has to pick
+/// between `log::error!`, `eprintln!` and a silent no-op in one place.
+///
+/// Without `std` or `log` there's nowhere to send the message, so it's
+/// dropped; the expression is still touched to avoid an unused-value warning.
+#[cfg(all(not(feature = "log"), not(feature = "std")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __unwrap_macros_report {
+    ($e:expr) => {
+        let _ = &$e;
+    };
+}
 
 #[macro_export]
 macro_rules! unwrap_or_else {
+    // Closure-like arms are listed first: a closure is itself a valid
+    // `$y:expr`/`$z:expr`, so the more specific `|...|`-prefixed patterns
+    // have to be tried before the generic ones or they'd never be reached.
+    //
+    // These arms don't auto-report: the whole point of supplying a closure
+    // is to take over the fallback, including any logging it wants to do
+    // itself. Add the `log` tag (below) to opt into the automatic report.
+    (Result, $x:expr, |$e:ident| $y:expr) => {
+        match $x {
+            Ok(val) => val,
+            Err($e) => $y,
+        }
+    };
+
+    (Option, $x:expr, |$e:ident| $y:expr) => {
+        match $x {
+            Some(val) => val,
+            None => {
+                let $e = ();
+                $y
+            }
+        }
+    };
+
+    // Same `Result` closure-like arm, opted into the automatic report by
+    // tagging the call with `log` before the closure. There's no `Option`
+    // equivalent: `None` carries no value, so there's nothing to report.
+    (Result, $x:expr, log, |$e:ident| $y:expr) => {
+        match $x {
+            Ok(val) => val,
+            Err($e) => {
+                $crate::__unwrap_macros_report!($e);
+                $y
+            }
+        }
+    };
+
+    // Closure-like arm that also moves in extra bound arguments, e.g.
+    // `unwrap_or_else!(Option, opt, |x1| x1 + 1, x)` binds `x1` to `x` in
+    // the `None` branch before evaluating the body.
+    (Option, $x:expr, |$($b:ident),+ $(,)?| $y:expr, $($arg:expr),+ $(,)?) => {
+        match $x {
+            Some(val) => val,
+            None => {
+                $(let $b = $arg;)+
+                $y
+            }
+        }
+    };
+
+    // `const fn`-compatible arms: a zero-argument closure marks "skip the
+    // reporting step" so the whole expansion is a plain `match`, with no
+    // `eprintln!`/`log::error!` call that would make it non-const-evaluable.
+    // `$v` may itself be `loop {}` or a call to another `const fn`.
+    (Result, $x:expr, || $v:expr) => {
+        match $x {
+            Ok(val) => val,
+            Err(_) => $v,
+        }
+    };
+
+    (Option, $x:expr, || $v:expr) => {
+        match $x {
+            Some(val) => val,
+            None => $v,
+        }
+    };
+
     (Result, $x:expr, $y:expr) => {
         match $x {
             Ok(val) => val,
             Err(e) => {
-                eprintln!("{}", e);
+                $crate::__unwrap_macros_report!(e);
                 $y
             }
         }
@@ -70,20 +253,13 @@ macro_rules! unwrap_or_else {
         match $x {
             Ok(val) | Some(val) => val,
             Err(e) => {
-                eprintln!("{}", e);
+                $crate::__unwrap_macros_report!(e);
                 $y
             }
             None => $y,
         }
     };
 
-    (Result, $x:expr, |$e:ident| $y:expr) => {
-        match $x {
-            Ok(val) => val,
-            Err($e) => $y,
-        }
-    };
-
     (Option, $x:expr, $y:expr) => {
         match $x {
             Some(val) => val,
@@ -95,9 +271,247 @@ macro_rules! unwrap_or_else {
         match $x {
             Some(val) => val,
             None => {
-                eprintln!("{}", $y);
+                $crate::__unwrap_macros_report!($y);
                 $z
             }
         }
     };
 }
+
+/// A drop-in replacement for `Result::unwrap`/`Option::unwrap` that panics
+/// with a message naming the failing expression, instead of pointing into
+/// `core/option.rs`.
+///
+/// ```should_panic
+/// # use unwrap_macros::unwrap;
+/// let thing: Option<i32> = None;
+/// let val = unwrap!(Option, thing);
+/// ```
+/// A custom message can be supplied, `format!`-style:
+/// ```should_panic
+/// # use unwrap_macros::unwrap;
+/// let thing: Option<i32> = None;
+/// let val = unwrap!(Option, thing, "expected a value for {}", "thing");
+/// ```
+#[macro_export]
+macro_rules! unwrap {
+    (Result, $e:expr) => {
+        match $e {
+            Ok(val) => val,
+            Err(err) => panic!("{}: {:?}", stringify!($e), err),
+        }
+    };
+
+    (Option, $e:expr) => {
+        match $e {
+            Some(val) => val,
+            None => panic!("{}: called `unwrap!` on a `None` value", stringify!($e)),
+        }
+    };
+
+    (Result, $e:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        match $e {
+            Ok(val) => val,
+            Err(err) => panic!(concat!("{}: ", $fmt, " ({:?})"), stringify!($e), $($arg,)* err),
+        }
+    };
+
+    (Option, $e:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        match $e {
+            Some(val) => val,
+            None => panic!(concat!("{}: ", $fmt), stringify!($e), $($arg),*),
+        }
+    };
+}
+
+/// Extracts a binding out of an arbitrary enum pattern, generalizing
+/// `unwrap_or_else!`/`unwrap!` beyond `Result`/`Option` to any "assume I'm in
+/// this state and grab its fields, otherwise bail" situation.
+///
+/// On a non-match it runs a control-flow expression (`continue`/`break`/
+/// `return`) given after `else`, or panics with a `stringify!`-based message
+/// if no `else` branch is given.
+///
+/// ```
+/// # use unwrap_macros::unwrap_variant;
+/// enum PatternElement {
+///     Tag { key_subtree: u32 },
+///     Text(String),
+/// }
+///
+/// let elements = vec![PatternElement::Tag { key_subtree: 1 }, PatternElement::Text("x".into())];
+/// for el in elements {
+///     let key = unwrap_variant!(PatternElement::Tag { key_subtree } = el => key_subtree, else continue);
+///     println!("{}", key);
+/// }
+/// ```
+#[macro_export]
+macro_rules! unwrap_variant {
+    ($pat:pat = $x:expr => $bind:expr, else $ctrl:expr) => {
+        if let $pat = $x {
+            $bind
+        } else {
+            $ctrl
+        }
+    };
+
+    ($pat:pat = $x:expr => $bind:expr) => {
+        if let $pat = $x {
+            $bind
+        } else {
+            panic!("value did not match `{}`", stringify!($pat))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    #[derive(Debug)]
+    enum MyError {
+        First,
+    }
+
+    impl fmt::Display for MyError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    enum PatternElement {
+        Tag { key_subtree: u32 },
+        Text(String),
+    }
+
+    #[test]
+    fn unwrap_or_else_result_continue_skips_errs() {
+        let some_stuff: Vec<Result<i32, MyError>> = vec![Ok(1), Err(MyError::First), Ok(2)];
+        let mut sum = 0;
+        for thing in some_stuff {
+            let val = unwrap_or_else!(Result, thing, continue);
+            sum += val;
+        }
+        assert_eq!(sum, 3);
+    }
+
+    #[test]
+    fn unwrap_or_else_result_closure_does_not_auto_report() {
+        let thing: Result<i32, MyError> = Err(MyError::First);
+        let val = unwrap_or_else!(Result, thing, |_e| 42);
+        assert_eq!(val, 42);
+    }
+
+    #[test]
+    fn unwrap_or_else_result_log_tag_still_runs_the_body() {
+        let thing: Result<i32, MyError> = Err(MyError::First);
+        let val = unwrap_or_else!(Result, thing, log, |_e| 7);
+        assert_eq!(val, 7);
+    }
+
+    #[test]
+    fn unwrap_or_else_option_default() {
+        let val = unwrap_or_else!(Option, None::<i32>, 9);
+        assert_eq!(val, 9);
+    }
+
+    #[test]
+    fn unwrap_or_else_option_with_message() {
+        let val = unwrap_or_else!(Option, None::<i32>, "missing", 5);
+        assert_eq!(val, 5);
+    }
+
+    #[test]
+    fn unwrap_or_else_option_closure() {
+        let val = unwrap_or_else!(Option, None::<i32>, |_e| 1);
+        assert_eq!(val, 1);
+    }
+
+    #[test]
+    fn unwrap_or_else_option_closure_with_bound_arg() {
+        let x = 10;
+        let val = unwrap_or_else!(Option, None::<i32>, |x1| x1 + 1, x);
+        assert_eq!(val, 11);
+    }
+
+    #[test]
+    fn unwrap_or_else_const_fn_arms() {
+        const fn first_or_zero(opt: Option<i32>) -> i32 {
+            unwrap_or_else!(Option, opt, || 0)
+        }
+        const FIRST: i32 = first_or_zero(None);
+        assert_eq!(FIRST, 0);
+        assert_eq!(first_or_zero(Some(4)), 4);
+    }
+
+    #[test]
+    fn unwrap_option_some_returns_the_value() {
+        assert_eq!(unwrap!(Option, Some(1)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "called `unwrap!` on a `None` value")]
+    fn unwrap_option_none_panics() {
+        let thing: Option<i32> = None;
+        unwrap!(Option, thing);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a value for thing")]
+    fn unwrap_option_custom_message_panics() {
+        let thing: Option<i32> = None;
+        unwrap!(Option, thing, "expected a value for {}", "thing");
+    }
+
+    #[test]
+    fn unwrap_result_ok_returns_the_value() {
+        let thing: Result<i32, MyError> = Ok(2);
+        assert_eq!(unwrap!(Result, thing), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "First")]
+    fn unwrap_result_err_panics_naming_the_error() {
+        let thing: Result<i32, MyError> = Err(MyError::First);
+        unwrap!(Result, thing);
+    }
+
+    #[test]
+    // Regression test: stringifying a brace-containing expression used to be
+    // spliced into the panic format literal, which failed to compile.
+    fn unwrap_handles_brace_containing_expressions() {
+        let val = unwrap!(Result, { let x: Result<i32, MyError> = Ok(3); x });
+        assert_eq!(val, 3);
+    }
+
+    #[test]
+    fn unwrap_variant_extracts_the_bound_field() {
+        let el = PatternElement::Tag { key_subtree: 5 };
+        let key = unwrap_variant!(PatternElement::Tag { key_subtree } = el => key_subtree, else panic!());
+        assert_eq!(key, 5);
+    }
+
+    #[test]
+    fn unwrap_variant_else_runs_control_flow_on_mismatch() {
+        let elements = vec![PatternElement::Text("x".into()), PatternElement::Tag { key_subtree: 7 }];
+        let mut found = None;
+        for el in elements {
+            if let PatternElement::Text(ref s) = el {
+                assert_eq!(s, "x");
+            }
+            let key = unwrap_variant!(PatternElement::Tag { key_subtree } = el => key_subtree, else continue);
+            found = Some(key);
+        }
+        assert_eq!(found, Some(7));
+    }
+
+    #[test]
+    // Regression test: stringifying a struct-variant pattern (which contains
+    // braces) used to be spliced into the panic format literal, which failed
+    // to compile.
+    #[should_panic(expected = "did not match")]
+    fn unwrap_variant_struct_pattern_panics_without_else() {
+        let el = PatternElement::Text("x".into());
+        let _key = unwrap_variant!(PatternElement::Tag { key_subtree } = el => key_subtree);
+    }
+}